@@ -1,10 +1,178 @@
+use std::ffi::{OsStr, OsString};
 use std::io::{BufRead, Write};
+use std::iter::Peekable;
+use std::os::unix::process::ExitStatusExt;
+use std::str::Chars;
 
 const PROMPT: &'static str = "> ";
 
-fn parse_line<'a>(line_buf: &'a str) -> Vec<&'a str> {
-    //TODO: Parsing with escapes and quotes and other shell things
-    line_buf.split_ascii_whitespace().collect()
+/// Why a line of input could not be tokenized.
+#[derive(Debug)]
+enum ParseError {
+    /// A `'` or `"` was opened but never closed.
+    UnterminatedQuote,
+    /// A `\` appeared as the very last character of the line.
+    TrailingBackslash,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnterminatedQuote => write!(f, "unterminated quote"),
+            ParseError::TrailingBackslash => write!(f, "trailing backslash"),
+        }
+    }
+}
+
+/// Tokenize a line of shell input.
+///
+/// Understands single quotes (literal, no escapes or expansion), double
+/// quotes (grouping, with backslash escapes and `$VAR` expansion still
+/// active inside), backslash escaping of the next character outside of
+/// quotes, and `$VAR`/`${VAR}` expansion against the process environment.
+///
+/// Tokens are built as `OsString`s rather than `String`s: an expanded
+/// environment variable may hold data that isn't valid UTF-8, and the
+/// `exec` family of syscalls only forbids interior NUL bytes, not non-UTF-8
+/// bytes, so there's no reason to reject it here.
+fn parse_line(line: &str) -> Result<Vec<OsString>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut current = OsString::new();
+    let mut in_token = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            '\'' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c.to_string()),
+                        None => return Err(ParseError::UnterminatedQuote),
+                    }
+                }
+            }
+            '"' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(next @ ('"' | '\\' | '$')) => current.push(next.to_string()),
+                            Some(other) => {
+                                current.push("\\");
+                                current.push(other.to_string());
+                            }
+                            None => return Err(ParseError::TrailingBackslash),
+                        },
+                        Some('$') => expand_variable(&mut chars, &mut current),
+                        Some(c) => current.push(c.to_string()),
+                        None => return Err(ParseError::UnterminatedQuote),
+                    }
+                }
+            }
+            '\\' => {
+                in_token = true;
+                match chars.next() {
+                    Some(next) => current.push(next.to_string()),
+                    None => return Err(ParseError::TrailingBackslash),
+                }
+            }
+            '$' => {
+                in_token = true;
+                expand_variable(&mut chars, &mut current);
+            }
+            c => {
+                in_token = true;
+                current.push(c.to_string());
+            }
+        }
+    }
+
+    if in_token {
+        tokens.push(current);
+    }
+
+    Ok(tokens)
+}
+
+/// Expand a `$VAR` or `${VAR}` reference (the leading `$` has already been
+/// consumed) against the process environment, appending the result to `out`.
+/// An unset variable expands to nothing.
+fn expand_variable(chars: &mut Peekable<Chars>, out: &mut OsString) {
+    let name = if chars.peek() == Some(&'{') {
+        chars.next();
+        chars.by_ref().take_while(|&c| c != '}').collect::<String>()
+    } else {
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                name.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        name
+    };
+
+    // `var_os` (rather than `var`) so a non-UTF-8 environment variable value
+    // is carried through as-is instead of being rejected.
+    if let Some(value) = std::env::var_os(&name) {
+        out.push(value);
+    }
+}
+
+/// Run `program` with `args`, reporting failures instead of panicking.
+///
+/// A typo'd command or a failed `exec` shouldn't take down the only shell
+/// on the system (and, via `init`, the machine along with it).
+fn run_command(program: &OsStr, args: &[OsString]) {
+    match std::process::Command::new(program).args(args).spawn() {
+        Ok(mut child) => match child.wait() {
+            Ok(status) => report_exit_status(program, status),
+            Err(error) => println!(
+                "ibish: error waiting for '{}': {}",
+                program.to_string_lossy(),
+                error
+            ),
+        },
+        Err(error) => report_spawn_error(program, error),
+    }
+}
+
+fn report_spawn_error(program: &OsStr, error: std::io::Error) {
+    let name = program.to_string_lossy();
+    match error.kind() {
+        std::io::ErrorKind::NotFound => println!("ibish: {}: command not found", name),
+        std::io::ErrorKind::PermissionDenied => println!("ibish: {}: permission denied", name),
+        std::io::ErrorKind::InvalidInput => {
+            println!("ibish: {}: argument contains a NUL byte", name)
+        }
+        _ => println!("ibish: {}: {}", name, error),
+    }
+}
+
+fn report_exit_status(program: &OsStr, status: std::process::ExitStatus) {
+    if status.success() {
+        return;
+    }
+    if let Some(signal) = status.signal() {
+        println!(
+            "ibish: {}: terminated by signal {}",
+            program.to_string_lossy(),
+            signal
+        );
+    } else if let Some(code) = status.code() {
+        println!("ibish: {}: exited with status {}", program.to_string_lossy(), code);
+    }
 }
 
 fn main() {
@@ -22,27 +190,28 @@ fn main() {
         stdout.flush().unwrap();
         stdin.read_line(&mut line_buf).unwrap();
 
-        // Parse the line
-        let parsed_line = parse_line(&line_buf);
+        // Parse the line, without the newline `read_line` keeps, so a
+        // trailing backslash is reported rather than escaping the newline
+        let parsed_line = match parse_line(line_buf.trim_end_matches(|c| c == '\n' || c == '\r')) {
+            Ok(tokens) => tokens,
+            Err(error) => {
+                println!("ibish: {}", error);
+                line_buf.clear();
+                continue;
+            }
+        };
 
         // Try to find some keywords which the shell will interpret directly
-        match parsed_line.as_slice() {
+        if parsed_line.is_empty() {
+            // Empty line, nothing to do
+        } else if parsed_line[0] == "exit" {
             // Leave the shell, ignore any other arguments
-            ["exit", ..] => std::process::exit(0),
-            &[] => {} // Empty line nothing to do
-            _ => {
-                // Line isn't empty and isn't a keyword, try to resolve the items
-                // Its safe to get this item (eg no panic) because we didn't match the empty
-                // slice pattern, so there is at least one item.
-                let path_or_name = parsed_line[0];
-                let args = &parsed_line[1..];
-
-                let mut child_process = std::process::Command::new(path_or_name)
-                    .args(args.iter())
-                    .spawn()
-                    .unwrap();
-                child_process.wait().unwrap();
-            }
+            std::process::exit(0);
+        } else {
+            // Line isn't empty and isn't a keyword, try to resolve the items.
+            // Its safe to get this item (eg no panic) because we just checked
+            // the line isn't empty, so there is at least one item.
+            run_command(&parsed_line[0], &parsed_line[1..]);
         }
 
         // Clear the line as `read_line` will just continue appending to our line buffer
@@ -8,3 +8,6 @@ pub const DEFAULT_BANNER_LOGO: &'static str = r#" _____ _     _
 
 /// Set the defaults for the PATH variable we want to set up
 pub const DEFAULT_PATH: &'static str = "/sbin;/bin";
+
+/// Default location of the service table `init` reads at boot
+pub const DEFAULT_SERVICE_TABLE_PATH: &'static str = "/etc/inittab.toml";
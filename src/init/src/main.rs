@@ -1,6 +1,8 @@
 mod boot;
 mod debug;
 mod defaults;
+mod reap;
+mod services;
 mod shutdown;
 
 fn main() {
@@ -16,15 +18,22 @@ fn main() {
     // We need a PATH or `ibish` won't work :(
     std::env::set_var("PATH", defaults::DEFAULT_PATH);
 
+    // As PID 1 we inherit every orphaned process in the system; make sure
+    // none of them become permanent zombies.
+    reap::block_sigchld();
+
+    let table = services::load_service_table(defaults::DEFAULT_SERVICE_TABLE_PATH);
+    let supervisor = services::Supervisor::new();
+    supervisor.spawn_all(&table);
+
     loop {
-        // Spawn one shell and then shutdown
-        if let Ok(mut child) = std::process::Command::new("/ibish").spawn() {
-            match child.wait() {
-                Ok(_) => {} //Nothing to do
-                Err(_) => println!("Error waiting for child to terminate"),
+        reap::wait_for_sigchld();
+        reap::reap_all(|pid| match supervisor.handle_exit(pid.as_raw()) {
+            services::ExitOutcome::NotSupervised => println!("Reaped orphaned process {}", pid),
+            services::ExitOutcome::ShutdownRequested(action) => {
+                shutdown::on_shutdown_request(action)
             }
-            // initiate shutdown.
-            shutdown::on_shutdown_request();
-        }
+            services::ExitOutcome::Stopped | services::ExitOutcome::Restarted => {}
+        });
     }
 }
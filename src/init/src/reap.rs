@@ -0,0 +1,58 @@
+use nix::errno::Errno;
+use nix::sys::signal::{self, sigprocmask, SigSet, SigmaskHow, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{getpid, Pid};
+
+fn sigchld_set() -> SigSet {
+    let mut set = SigSet::empty();
+    set.add(Signal::SIGCHLD);
+    set
+}
+
+/// Block `SIGCHLD` process-wide so it queues as pending instead of being
+/// discarded by its default disposition (ignore).
+///
+/// As PID 1 we inherit every orphaned process in the system, so we must
+/// reap anything that dies, not just the services we spawned ourselves.
+/// `wait_for_sigchld` is what actually consumes the signal.
+pub fn block_sigchld() {
+    if let Err(_error) = sigprocmask(SigmaskHow::SIG_BLOCK, Some(&sigchld_set()), None) {
+        crate::debug::unrecoverable_error("Could not block SIGCHLD");
+    }
+}
+
+/// Block until a `SIGCHLD` is pending, then accept it.
+///
+/// `SigSet::wait` wraps `sigwait(3)`, which atomically waits for one of the
+/// given (blocked) signals to become pending and dequeues it. Unlike a
+/// handler-plus-flag design, there's no window between "check for work" and
+/// "go to sleep" where a delivery could land and be missed.
+pub fn wait_for_sigchld() {
+    if let Err(_error) = sigchld_set().wait() {
+        crate::debug::unrecoverable_error("Could not wait for SIGCHLD");
+    }
+}
+
+/// Wake whatever is blocked in [`wait_for_sigchld`], as if a `SIGCHLD` had
+/// just arrived. Used by background work (e.g. a delayed service respawn)
+/// that needs the supervisor loop to notice a change it made.
+pub fn wake() {
+    let _ = signal::kill(getpid(), Signal::SIGCHLD);
+}
+
+/// Reap every child that has exited, calling `on_exit` for each one.
+///
+/// A single `SIGCHLD` delivery can coalesce several exits, so we keep
+/// calling `waitpid` until there is nothing left to reap.
+pub fn reap_all<F: FnMut(Pid)>(mut on_exit: F) {
+    loop {
+        match waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
+            Ok(WaitStatus::StillAlive) => break,
+            Err(Errno::ECHILD) => break,
+            Ok(WaitStatus::Exited(pid, _)) | Ok(WaitStatus::Signaled(pid, _, _)) => on_exit(pid),
+            // Stopped/continued notifications aren't an exit; keep reaping.
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::shutdown::ShutdownAction;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to wait before respawning a crash-looping service, so a service
+/// that fails immediately on every start can't spin the CPU.
+const RESPAWN_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How `init` should react when a service exits.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Run once at boot; if it exits, leave it dead.
+    Once,
+    /// Run once at boot; when it exits, the system should shut down.
+    ///
+    /// This is the historical behavior of launching a single `/ibish`.
+    Wait,
+    /// Restart the service whenever it exits.
+    Respawn,
+}
+
+/// One entry of the service table (e.g. a line of `/etc/inittab.toml`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceEntry {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    pub action: Action,
+    /// Which `ShutdownAction` to request once this entry exits.
+    ///
+    /// Only meaningful for `Action::Wait` entries; ignored otherwise.
+    #[serde(default)]
+    pub shutdown_action: ShutdownAction,
+}
+
+impl ServiceEntry {
+    fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        command.envs(&self.env);
+        if let Some(dir) = &self.working_dir {
+            command.current_dir(dir);
+        }
+
+        // `init` blocks SIGCHLD process-wide so it can `sigwait` for it (see
+        // `reap::block_sigchld`); that block is otherwise inherited straight
+        // through to the child, silently breaking its own SIGCHLD-driven
+        // child reaping (exactly what this init system exists to prevent,
+        // just pushed one process down). Undo it before exec.
+        //
+        // Safety: `SigSet::thread_set_mask` only calls `sigprocmask`, which
+        // is async-signal-safe, so it's sound to run between `fork` and `exec`.
+        unsafe {
+            command.pre_exec(|| {
+                nix::sys::signal::SigSet::empty().thread_set_mask()?;
+                Ok(())
+            });
+        }
+
+        command
+    }
+}
+
+/// The on-disk shape of the service table.
+#[derive(Debug, Default, Deserialize)]
+struct ServiceTable {
+    #[serde(default, rename = "service")]
+    service: Vec<ServiceEntry>,
+}
+
+/// Read the service table at `path`.
+///
+/// If the table is missing or fails to parse, we fall back to the
+/// historical `ibis` behavior of launching a single `/ibish` and shutting
+/// down once it exits.
+pub fn load_service_table(path: &str) -> Vec<ServiceEntry> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => match toml::from_str::<ServiceTable>(&contents) {
+            Ok(table) => table.service,
+            Err(error) => {
+                println!("Could not parse service table {}: {}", path, error);
+                default_service_table()
+            }
+        },
+        Err(_) => default_service_table(),
+    }
+}
+
+fn default_service_table() -> Vec<ServiceEntry> {
+    vec![ServiceEntry {
+        program: "/ibish".to_string(),
+        args: Vec::new(),
+        env: HashMap::new(),
+        working_dir: None,
+        action: Action::Wait,
+        shutdown_action: ShutdownAction::PowerOff,
+    }]
+}
+
+/// What happened as a result of the reaper observing a process exit.
+pub enum ExitOutcome {
+    /// This PID wasn't a service we're supervising.
+    NotSupervised,
+    /// A `once` service exited and will not be restarted.
+    Stopped,
+    /// A `respawn` service exited and a restart has been scheduled after
+    /// the backoff.
+    Restarted,
+    /// A `wait` service exited; the system should shut down this way.
+    ShutdownRequested(ShutdownAction),
+}
+
+/// Tracks the live PID for every supervised service and decides what to do
+/// when one exits.
+///
+/// Shared (rather than owned outright) because a delayed respawn registers
+/// its new PID from its own background thread: the same lock a respawn
+/// holds while spawning and registering is also what `handle_exit` takes to
+/// look a PID up, so the reaper can never observe a respawned child's exit
+/// before that child is actually in the map.
+pub struct Supervisor {
+    running: Arc<Mutex<HashMap<i32, ServiceEntry>>>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self {
+            running: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Spawn every entry in the table and start supervising it.
+    pub fn spawn_all(&self, entries: &[ServiceEntry]) {
+        for entry in entries {
+            self.spawn_entry(entry.clone());
+        }
+    }
+
+    fn spawn_entry(&self, entry: ServiceEntry) {
+        let mut running = self.running.lock().unwrap();
+        match entry.to_command().spawn() {
+            Ok(child) => {
+                running.insert(child.id() as i32, entry);
+            }
+            Err(error) => println!("Could not start service '{}': {}", entry.program, error),
+        }
+    }
+
+    /// Handle the reaper observing `pid` exit.
+    pub fn handle_exit(&self, pid: i32) -> ExitOutcome {
+        let entry = self.running.lock().unwrap().remove(&pid);
+        let Some(entry) = entry else {
+            return ExitOutcome::NotSupervised;
+        };
+
+        match entry.action {
+            Action::Once => {
+                println!("Service '{}' exited, not restarting", entry.program);
+                ExitOutcome::Stopped
+            }
+            Action::Wait => {
+                println!("Service '{}' exited", entry.program);
+                ExitOutcome::ShutdownRequested(entry.shutdown_action)
+            }
+            Action::Respawn => {
+                println!("Service '{}' exited, respawning", entry.program);
+                self.schedule_respawn(entry);
+                ExitOutcome::Restarted
+            }
+        }
+    }
+
+    /// Respawn `entry` after the backoff on its own thread, rather than
+    /// blocking the caller: the supervisor loop also drives reaping and
+    /// shutdown detection, and a crash-looping service shouldn't be able to
+    /// stall either while it waits out its backoff.
+    fn schedule_respawn(&self, entry: ServiceEntry) {
+        let running = Arc::clone(&self.running);
+        std::thread::spawn(move || {
+            std::thread::sleep(RESPAWN_BACKOFF);
+
+            // Hold the lock across spawn *and* registration: `handle_exit`
+            // takes the same lock before it can look the new PID up, so even
+            // if the child exits (and is reaped) the instant it's spawned,
+            // the reaper blocks on this lock until the entry is in the map
+            // instead of ever seeing it as an untracked orphan.
+            let mut running = running.lock().unwrap();
+            match entry.to_command().spawn() {
+                Ok(child) => {
+                    running.insert(child.id() as i32, entry);
+                }
+                Err(error) => {
+                    println!("Could not respawn service '{}': {}", entry.program, error)
+                }
+            }
+            drop(running);
+
+            // Wake the supervisor loop so it notices the new service right
+            // away instead of waiting for an unrelated SIGCHLD.
+            crate::reap::wake();
+        });
+    }
+}
@@ -1,28 +1,106 @@
 use crate::debug::unrecoverable_error;
+use nix::errno::Errno;
+use nix::sys::reboot::RebootMode;
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::Pid;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
 
-/// Perform a graceful shutdown of the system
+/// How long to give processes to exit cleanly after `SIGTERM` before we
+/// force them with `SIGKILL`.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How often to poll for exited children while waiting out the grace period.
+const REAP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What the system should do once every process has been terminated.
+///
+/// Selected per the service table's `wait` entry (see `services::ServiceEntry`),
+/// which is the "shutdown request" that fires once that entry exits.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShutdownAction {
+    /// Power the machine off.
+    PowerOff,
+    /// Reboot the machine.
+    Reboot,
+    /// Halt the machine without powering it off.
+    Halt,
+}
+
+impl Default for ShutdownAction {
+    /// Defaults to the historical `ibis` behavior: powering off.
+    fn default() -> Self {
+        ShutdownAction::PowerOff
+    }
+}
+
+impl ShutdownAction {
+    fn reboot_mode(self) -> RebootMode {
+        match self {
+            ShutdownAction::PowerOff => RebootMode::RB_POWER_OFF,
+            ShutdownAction::Reboot => RebootMode::RB_AUTOBOOT,
+            ShutdownAction::Halt => RebootMode::RB_HALT_SYSTEM,
+        }
+    }
+}
+
+/// Perform a graceful shutdown of the system, then carry out `action`.
 ///
-/// There are several stages here:
-///  1. Terminate all processes in the system
-///  2. Sync the filesystem
-///  3. Inform the kernel to shutdown and power-off
-pub fn on_shutdown_request() {
+/// This is the standard two-phase termination:
+///  1. Send `SIGTERM` to every process and give them a grace period to exit.
+///  2. If stragglers remain once the grace period elapses, send `SIGKILL`.
+///  3. Sync the filesystem and tell the kernel to carry out `action`.
+pub fn on_shutdown_request(action: ShutdownAction) {
     println!("Terminating all processes");
-    // Setting PID to -1 indicates we want to kill every process we have
-    // permission to do so (man 3 kill). In this case it should be everything
-    // because we are `init`
-    if let Err(_error) = nix::sys::signal::kill(
-        nix::unistd::Pid::from_raw(-1),
-        nix::sys::signal::Signal::SIGTERM,
-    ) {
-        println!("Failure trying to kill processes during shutdown");
+    terminate_all(Signal::SIGTERM);
+
+    if !wait_for_all_children(SHUTDOWN_GRACE_PERIOD) {
+        println!("Processes did not exit in time, forcing shutdown");
+        terminate_all(Signal::SIGKILL);
+        // SIGKILL can't be caught or ignored, but give the kernel the same
+        // deadline to actually deliver and reap it rather than looping forever.
+        wait_for_all_children(SHUTDOWN_GRACE_PERIOD);
     }
 
     // Per the documentaiton (`man 3 reboot`) we must issue a `sync` prior
-    // to using `RB_POWER_OFF` or else we could lose data.
+    // to using any reboot mode below or else we could lose data.
     // This would make our users very unhappy
     nix::unistd::sync();
-    if let Err(_error) = nix::sys::reboot::reboot(nix::sys::reboot::RebootMode::RB_POWER_OFF) {
+    if let Err(_error) = nix::sys::reboot::reboot(action.reboot_mode()) {
         unrecoverable_error("Could not initiate shutdown");
     }
 }
+
+fn terminate_all(signal: Signal) {
+    // Setting PID to -1 indicates we want to signal every process we have
+    // permission to do so (man 3 kill). In this case it should be everything
+    // because we are `init`
+    if let Err(_error) = kill(Pid::from_raw(-1), signal) {
+        println!("Failure trying to signal processes during shutdown");
+    }
+}
+
+/// Reap children until none are left or `timeout` elapses.
+///
+/// Returns `true` if every child was reaped before the deadline.
+fn wait_for_all_children(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
+            // No child has exited yet, but some are still alive; keep polling.
+            Ok(WaitStatus::StillAlive) => {
+                if Instant::now() >= deadline {
+                    return false;
+                }
+                std::thread::sleep(REAP_POLL_INTERVAL);
+            }
+            // No children left at all.
+            Err(Errno::ECHILD) => return true,
+            // Reaped one; check again immediately in case more are ready.
+            Ok(_) => {}
+            Err(_) => return true,
+        }
+    }
+}